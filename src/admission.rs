@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use axum::{routing::post, Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use k8s_openapi::api::admission::v1::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use k8s_openapi::api::admissionregistration::v1::{
+    RuleWithOperations, ServiceReference, ValidatingWebhook, ValidatingWebhookConfiguration,
+    WebhookClientConfig,
+};
+use k8s_openapi::api::core::v1::{Secret, Service, ServicePort, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use k8s_openapi::ByteString;
+use kube::api::{Patch, PatchParams, PostParams};
+use kube::{Api, Client};
+use tracing::{error, info};
+
+/// Where and under what names the admission webhook registers itself.
+pub struct AdmissionConfig {
+    pub namespace: String,
+    pub service_name: String,
+    pub webhook_config_name: String,
+    pub port: u16,
+    /// `failurePolicy` for the registered `ValidatingWebhookConfiguration`
+    /// ("Fail" or "Ignore"). Defaults to "Fail" so a crashed or unreachable
+    /// webhook denies admission instead of silently letting secrets through.
+    pub failure_policy: String,
+}
+
+pub struct ServingCert {
+    pub cert_pem: Vec<u8>,
+    pub key_pem: Vec<u8>,
+}
+
+/// Generates a self-signed serving certificate covering the in-cluster DNS
+/// names the API server uses to reach this pod's `Service`.
+fn generate_serving_cert(cfg: &AdmissionConfig) -> Result<ServingCert> {
+    let names = vec![
+        format!("{}.{}.svc", cfg.service_name, cfg.namespace),
+        format!("{}.{}.svc.cluster.local", cfg.service_name, cfg.namespace),
+    ];
+    let certified = rcgen::generate_simple_self_signed(names)
+        .context("failed to generate self-signed admission webhook certificate")?;
+
+    Ok(ServingCert {
+        cert_pem: certified.cert.pem().into_bytes(),
+        key_pem: certified.signing_key.serialize_pem().into_bytes(),
+    })
+}
+
+fn serving_cert_from_secret(secret: &Secret) -> Option<ServingCert> {
+    let data = secret.data.as_ref()?;
+    let cert_pem = data.get("tls.crt")?;
+    let key_pem = data.get("tls.key")?;
+    Some(ServingCert {
+        cert_pem: cert_pem.0.clone(),
+        key_pem: key_pem.0.clone(),
+    })
+}
+
+/// Reads a previously-persisted serving certificate from the
+/// `{service_name}-tls` `Secret`, or generates and persists a new one if
+/// none exists yet. With more than one replica sharing the same `Service`,
+/// generating a fresh self-signed cert per process would mean each pod keeps
+/// overwriting the others' CA bundle in the `ValidatingWebhookConfiguration`,
+/// so the cert has to be settled on once and reused across restarts and
+/// replicas instead.
+///
+/// The persist step uses `create` rather than a `force`-applied `patch`: two
+/// replicas racing on first boot would otherwise each generate a different
+/// cert and each overwrite the other's, leaving the `ValidatingWebhookConfiguration`
+/// trusting only whichever one patched last. `create` lets Kubernetes pick a
+/// single winner; the loser gets `AlreadyExists` and re-reads the winner's
+/// cert instead of registering with its own locally-generated one.
+pub async fn load_or_generate_serving_cert(client: Client, cfg: &AdmissionConfig) -> Result<ServingCert> {
+    let secrets: Api<Secret> = Api::namespaced(client, &cfg.namespace);
+    let tls_secret_name = format!("{}-tls", cfg.service_name);
+
+    if let Ok(existing) = secrets.get(&tls_secret_name).await {
+        if let Some(cert) = serving_cert_from_secret(&existing) {
+            info!("Reusing existing admission webhook serving certificate from Secret {}", tls_secret_name);
+            return Ok(cert);
+        }
+    }
+
+    info!(
+        "No existing serving certificate found; generating one and persisting it to Secret {}",
+        tls_secret_name
+    );
+    let cert = generate_serving_cert(cfg)?;
+
+    let tls_secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(tls_secret_name.clone()),
+            namespace: Some(cfg.namespace.clone()),
+            ..Default::default()
+        },
+        type_: Some("kubernetes.io/tls".to_string()),
+        data: Some(BTreeMap::from([
+            ("tls.crt".to_string(), ByteString(cert.cert_pem.clone())),
+            ("tls.key".to_string(), ByteString(cert.key_pem.clone())),
+        ])),
+        ..Default::default()
+    };
+
+    match secrets.create(&PostParams::default(), &tls_secret).await {
+        Ok(_) => Ok(cert),
+        Err(kube::Error::Api(resp)) if resp.code == 409 => {
+            info!("Another replica already persisted a serving certificate; using theirs instead of ours");
+            let existing = secrets
+                .get(&tls_secret_name)
+                .await
+                .context("failed to re-read admission webhook serving certificate after a create race")?;
+            serving_cert_from_secret(&existing)
+                .context("existing admission webhook TLS Secret is missing tls.crt/tls.key")
+        }
+        Err(e) => Err(e).context("failed to persist admission webhook serving certificate"),
+    }
+}
+
+/// Creates or patches the `Service`, the `Secret` holding the CA bundle, and
+/// the `ValidatingWebhookConfiguration` so the API server trusts and calls
+/// this pod for `Secret` CREATE/UPDATE admission.
+pub async fn ensure_registered(client: Client, cfg: &AdmissionConfig, cert: &ServingCert) -> Result<()> {
+    let apply_params = PatchParams::apply("k8s-secrets-admission").force();
+
+    let services: Api<Service> = Api::namespaced(client.clone(), &cfg.namespace);
+    let service = Service {
+        metadata: ObjectMeta {
+            name: Some(cfg.service_name.clone()),
+            namespace: Some(cfg.namespace.clone()),
+            ..Default::default()
+        },
+        spec: Some(ServiceSpec {
+            selector: Some(BTreeMap::from([("app".to_string(), "k8s-secrets".to_string())])),
+            ports: Some(vec![ServicePort {
+                port: 443,
+                target_port: Some(IntOrString::Int(cfg.port as i32)),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    services
+        .patch(&cfg.service_name, &apply_params, &Patch::Apply(&service))
+        .await
+        .context("failed to apply admission webhook Service")?;
+
+    let ca_secret_name = format!("{}-ca", cfg.service_name);
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &cfg.namespace);
+    let ca_secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(ca_secret_name.clone()),
+            namespace: Some(cfg.namespace.clone()),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([(
+            "ca-bundle.pem".to_string(),
+            ByteString(cert.cert_pem.clone()),
+        )])),
+        ..Default::default()
+    };
+    secrets
+        .patch(&ca_secret_name, &apply_params, &Patch::Apply(&ca_secret))
+        .await
+        .context("failed to apply admission webhook CA Secret")?;
+
+    let webhooks: Api<ValidatingWebhookConfiguration> = Api::all(client);
+    let config = ValidatingWebhookConfiguration {
+        metadata: ObjectMeta {
+            name: Some(cfg.webhook_config_name.clone()),
+            ..Default::default()
+        },
+        webhooks: Some(vec![ValidatingWebhook {
+            name: cfg.webhook_config_name.clone(),
+            client_config: WebhookClientConfig {
+                service: Some(ServiceReference {
+                    name: cfg.service_name.clone(),
+                    namespace: cfg.namespace.clone(),
+                    path: Some("/admission".to_string()),
+                    port: Some(443),
+                }),
+                ca_bundle: Some(ByteString(cert.cert_pem.clone())),
+                ..Default::default()
+            },
+            rules: Some(vec![RuleWithOperations {
+                api_groups: Some(vec!["".to_string()]),
+                api_versions: Some(vec!["v1".to_string()]),
+                operations: Some(vec!["CREATE".to_string(), "UPDATE".to_string()]),
+                resources: Some(vec!["secrets".to_string()]),
+                scope: None,
+            }]),
+            side_effects: "None".to_string(),
+            admission_review_versions: vec!["v1".to_string()],
+            failure_policy: Some(cfg.failure_policy.clone()),
+            ..Default::default()
+        }]),
+    };
+    webhooks
+        .patch(&cfg.webhook_config_name, &apply_params, &Patch::Apply(&config))
+        .await
+        .context("failed to apply ValidatingWebhookConfiguration")?;
+
+    Ok(())
+}
+
+/// Rejects secrets with no data at all, and any `otpauth://` field that
+/// doesn't parse as a valid TOTP URL (the same parser `secret_handler` uses
+/// to mint codes, so a broken field here would otherwise fail silently
+/// at read time instead of at admission time).
+fn evaluate_policy(secret: &Secret) -> Result<(), String> {
+    let data = secret.data.clone().unwrap_or_default();
+    let string_data = secret.string_data.clone().unwrap_or_default();
+
+    if data.is_empty() && string_data.is_empty() {
+        return Err("secret has neither `data` nor `stringData`".to_string());
+    }
+
+    for (key, value) in &string_data {
+        if value.starts_with("otpauth://") && totp_rs::TOTP::from_url(value).is_err() {
+            return Err(format!("field '{key}' looks like an otpauth:// URL but failed to parse"));
+        }
+    }
+    for (key, value) in &data {
+        let decoded = String::from_utf8_lossy(&value.0);
+        if decoded.starts_with("otpauth://") && totp_rs::TOTP::from_url(&decoded).is_err() {
+            return Err(format!("field '{key}' looks like an otpauth:// URL but failed to parse"));
+        }
+    }
+
+    Ok(())
+}
+
+fn build_response(request: &AdmissionRequest) -> AdmissionResponse {
+    let response = AdmissionResponse::from(request);
+
+    let secret: Option<Secret> = request
+        .object
+        .as_ref()
+        .and_then(|raw| serde_json::from_value(raw.0.clone()).ok());
+
+    match secret {
+        None => response.deny("could not decode Secret object from admission request"),
+        Some(secret) => match evaluate_policy(&secret) {
+            Ok(()) => response,
+            Err(reason) => response.deny(reason),
+        },
+    }
+}
+
+async fn admission_handler(Json(review): Json<AdmissionReview>) -> Json<AdmissionReview> {
+    let Some(request) = review.request.as_ref() else {
+        error!("Admission review had no request body");
+        return Json(review);
+    };
+
+    info!("Admission review for secret {:?}", request.name);
+
+    Json(AdmissionReview {
+        types: review.types.clone(),
+        request: None,
+        response: Some(build_response(request)),
+    })
+}
+
+/// Runs the admission webhook's own TLS listener. This is deliberately a
+/// separate server from the main `axum::serve` loop: the API server talks
+/// to it only over HTTPS, on the port wired up in the `Service`/`ValidatingWebhookConfiguration`.
+pub async fn serve(cfg: AdmissionConfig, cert: ServingCert) -> Result<()> {
+    let tls_config = RustlsConfig::from_pem(cert.cert_pem, cert.key_pem)
+        .await
+        .context("failed to build TLS config for admission webhook")?;
+
+    let app = Router::new().route("/admission", post(admission_handler));
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], cfg.port));
+    info!("Admission webhook listening on {}", addr);
+
+    axum_server::bind_rustls(addr, tls_config)
+        .serve(app.into_make_service())
+        .await
+        .context("admission webhook server failed")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::runtime::RawExtension;
+
+    fn secret_with(data: Option<BTreeMap<String, ByteString>>, string_data: Option<BTreeMap<String, String>>) -> Secret {
+        Secret {
+            data,
+            string_data,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_policy_allows_a_secret_with_data() {
+        let secret = secret_with(
+            Some(BTreeMap::from([("key".to_string(), ByteString(b"value".to_vec()))])),
+            None,
+        );
+        assert!(evaluate_policy(&secret).is_ok());
+    }
+
+    #[test]
+    fn evaluate_policy_allows_a_secret_with_string_data() {
+        let secret = secret_with(None, Some(BTreeMap::from([("key".to_string(), "value".to_string())])));
+        assert!(evaluate_policy(&secret).is_ok());
+    }
+
+    #[test]
+    fn evaluate_policy_denies_a_secret_with_no_data_at_all() {
+        let secret = secret_with(None, None);
+        let err = evaluate_policy(&secret).unwrap_err();
+        assert!(err.contains("neither"), "unexpected message: {err}");
+    }
+
+    #[test]
+    fn evaluate_policy_denies_a_malformed_otpauth_url_in_string_data() {
+        let secret = secret_with(
+            None,
+            Some(BTreeMap::from([("totp".to_string(), "otpauth://not-a-valid-url".to_string())])),
+        );
+        let err = evaluate_policy(&secret).unwrap_err();
+        assert!(err.contains("otpauth"), "unexpected message: {err}");
+    }
+
+    #[test]
+    fn evaluate_policy_denies_a_malformed_otpauth_url_in_data() {
+        let secret = secret_with(
+            Some(BTreeMap::from([(
+                "totp".to_string(),
+                ByteString(b"otpauth://not-a-valid-url".to_vec()),
+            )])),
+            None,
+        );
+        let err = evaluate_policy(&secret).unwrap_err();
+        assert!(err.contains("otpauth"), "unexpected message: {err}");
+    }
+
+    fn request_with_object(object: Option<serde_json::Value>) -> AdmissionRequest {
+        AdmissionRequest {
+            object: object.map(RawExtension),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_response_allows_a_valid_secret() {
+        let secret = secret_with(
+            Some(BTreeMap::from([("key".to_string(), ByteString(b"value".to_vec()))])),
+            None,
+        );
+        let request = request_with_object(Some(serde_json::to_value(&secret).unwrap()));
+
+        let response = build_response(&request);
+
+        assert!(response.allowed);
+    }
+
+    #[test]
+    fn build_response_denies_a_secret_with_no_data() {
+        let secret = secret_with(None, None);
+        let request = request_with_object(Some(serde_json::to_value(&secret).unwrap()));
+
+        let response = build_response(&request);
+
+        assert!(!response.allowed);
+    }
+
+    #[test]
+    fn build_response_denies_when_the_object_cannot_be_decoded() {
+        let request = request_with_object(None);
+
+        let response = build_response(&request);
+
+        assert!(!response.allowed);
+    }
+}
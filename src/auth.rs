@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::AppState;
+
+/// A single entry in the `--api-keys-file`. Only the BLAKE3 hash of `key`
+/// is ever kept in memory; the raw key is dropped once it's been hashed.
+#[derive(Debug, Deserialize)]
+struct ApiKeyEntry {
+    key: String,
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    allowed_secrets: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyMeta {
+    pub expires_at: Option<DateTime<Utc>>,
+    pub allowed_secrets: Option<Vec<String>>,
+}
+
+pub type ApiKeyStore = HashMap<blake3::Hash, KeyMeta>;
+
+pub fn load_api_keys(path: &Path) -> Result<ApiKeyStore> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read api keys file {:?}", path))?;
+    let entries: Vec<ApiKeyEntry> = serde_json::from_str(&raw)
+        .with_context(|| format!("failed to parse api keys file {:?}", path))?;
+
+    let mut store = HashMap::with_capacity(entries.len());
+    for entry in entries {
+        let hash = blake3::hash(entry.key.as_bytes());
+        store.insert(
+            hash,
+            KeyMeta {
+                expires_at: entry.expires_at,
+                allowed_secrets: entry.allowed_secrets,
+            },
+        );
+    }
+    Ok(store)
+}
+
+fn extract_api_key(headers: &axum::http::HeaderMap) -> Option<String> {
+    if let Some(value) = headers.get("x-api-key") {
+        return value.to_str().ok().map(str::to_string);
+    }
+
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ").map(str::to_string)
+}
+
+/// Validates the API key (if any) presented on `headers` and returns its
+/// metadata. Shared by the `require_api_key` middleware and by callers that
+/// need to compose key auth with another gate (e.g. session login) instead
+/// of stacking both as a hard requirement.
+///
+/// The presented key is hashed with BLAKE3 before lookup; `blake3::Hash`'s
+/// `PartialEq` is constant-time, so the comparison doesn't leak timing
+/// information about how much of a wrong key matched.
+pub fn authenticate(state: &AppState, headers: &axum::http::HeaderMap) -> Result<KeyMeta, StatusCode> {
+    let presented = extract_api_key(headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    let hash = blake3::hash(presented.as_bytes());
+
+    let meta = {
+        let keys = state
+            .api_keys
+            .read()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        keys.get(&hash).cloned().ok_or(StatusCode::FORBIDDEN)?
+    };
+
+    if let Some(expires_at) = meta.expires_at {
+        if expires_at < Utc::now() {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    Ok(meta)
+}
+
+/// Axum middleware that rejects any request without a valid API key. On
+/// success, the matched key's metadata is stashed in request extensions so
+/// downstream handlers (e.g. `secret_handler`) can enforce per-key secret
+/// scoping.
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let meta = authenticate(&state, request.headers())?;
+    request.extensions_mut().insert(meta);
+    Ok(next.run(request).await)
+}
+
+/// Returns whether a key is allowed to read `secret_name`. Keys without an
+/// `allowed_secrets` list are unscoped and can read anything.
+pub fn secret_allowed(meta: &KeyMeta, secret_name: &str) -> bool {
+    match &meta.allowed_secrets {
+        Some(allowed) => allowed.iter().any(|s| s == secret_name),
+        None => true,
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Secret;
+use kube::runtime::watcher;
+use kube::{Api, Client};
+use tracing::error;
+
+#[derive(Clone)]
+pub struct CachedSecret {
+    pub data: Vec<(String, String)>,
+    pub last_synced: DateTime<Utc>,
+}
+
+pub type SecretCache = HashMap<String, CachedSecret>;
+
+fn decode(secret: &Secret) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+
+    if let Some(data) = &secret.data {
+        for (key, value) in data {
+            pairs.push((key.clone(), String::from_utf8_lossy(&value.0).to_string()));
+        }
+    } else if let Some(string_data) = &secret.string_data {
+        for (key, value) in string_data {
+            pairs.push((key.clone(), value.clone()));
+        }
+    }
+
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    pairs
+}
+
+fn upsert(cache: &RwLock<SecretCache>, name: &str, secret: &Secret) {
+    let Ok(mut cache) = cache.write() else {
+        error!("Secret cache lock poisoned; dropping update for {}", name);
+        return;
+    };
+    cache.insert(
+        name.to_string(),
+        CachedSecret {
+            data: decode(secret),
+            last_synced: Utc::now(),
+        },
+    );
+}
+
+fn remove(cache: &RwLock<SecretCache>, name: &str) {
+    let Ok(mut cache) = cache.write() else {
+        error!("Secret cache lock poisoned; dropping removal for {}", name);
+        return;
+    };
+    cache.remove(name);
+}
+
+/// Spawns a background watch for a single configured secret and keeps
+/// `cache` reconciled against Added/Modified/Deleted events. `watcher`
+/// already relists and re-establishes the watch on disconnect, so this
+/// task runs for the lifetime of the process.
+pub fn spawn_watch(client: Client, namespace: String, secret_name: String, cache: Arc<RwLock<SecretCache>>) {
+    tokio::spawn(async move {
+        let api: Api<Secret> = Api::namespaced(client, &namespace);
+        let config = watcher::Config::default().fields(&format!("metadata.name={secret_name}"));
+        let mut events = watcher(api, config).boxed();
+
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(watcher::Event::Applied(secret)) => upsert(&cache, &secret_name, &secret),
+                Ok(watcher::Event::Deleted(_)) => remove(&cache, &secret_name),
+                Ok(watcher::Event::Restarted(secrets)) => match secrets.first() {
+                    Some(secret) => upsert(&cache, &secret_name, secret),
+                    None => remove(&cache, &secret_name),
+                },
+                Err(e) => error!("Watch error for secret {}: {}", secret_name, e),
+            }
+        }
+    });
+}
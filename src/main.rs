@@ -1,23 +1,38 @@
+mod admission;
+mod auth;
+mod cache;
+mod metrics;
+mod session;
+mod webhook;
+
 use anyhow::Result;
 use askama::Template;
 use axum::{
-    extract::{Json, Query, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{Extension, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
     response::{Html, IntoResponse, Response},
     routing::{get, post},
     Router,
 };
-use chrono;
+use chrono::{self, DateTime, Utc};
 use clap::Parser;
+use ed25519_dalek::VerifyingKey;
 use k8s_openapi::api::core::v1::Secret;
 use kube::{Api, Client};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use totp_rs::TOTP;
 use tracing::{error, info};
 use tracing_subscriber;
 
+use auth::{ApiKeyStore, KeyMeta};
+use metrics::Metrics;
+use session::{Session, UserStore};
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -32,6 +47,37 @@ struct Args {
 
     #[arg(short = 'w', long, help = "Enable webhook endpoint at /webhook")]
     webhook: bool,
+
+    #[arg(long, help = "Path to a JSON file of API keys required to reach secret-serving routes")]
+    api_keys_file: Option<PathBuf>,
+
+    #[arg(long, help = "Path to a hex-encoded Ed25519 public key; required signer for /webhook requests")]
+    webhook_pubkey: Option<PathBuf>,
+
+    #[arg(long, default_value_t = webhook::DEFAULT_MAX_SKEW_SECS, help = "Reject signed webhooks older than this many seconds")]
+    webhook_max_skew_secs: i64,
+
+    #[arg(long, help = "Register as a Kubernetes ValidatingAdmissionWebhook and serve /admission over TLS")]
+    admission_webhook: bool,
+
+    #[arg(long, default_value_t = 8443, help = "Port the admission webhook TLS listener binds to")]
+    admission_port: u16,
+
+    #[arg(long, default_value = "k8s-secrets-admission", help = "Service name to register for the admission webhook")]
+    admission_service_name: String,
+
+    #[arg(long, default_value = "k8s-secrets-admission.k8s-secrets.io", help = "ValidatingWebhookConfiguration name")]
+    admission_webhook_config_name: String,
+
+    #[arg(
+        long,
+        default_value = "Fail",
+        help = "ValidatingWebhookConfiguration failurePolicy (Fail or Ignore); Fail denies admission if the webhook is unreachable"
+    )]
+    admission_failure_policy: String,
+
+    #[arg(long, help = "Path to a JSON file of username -> Argon2id password hash; gates the web UI behind /login")]
+    auth_users_file: Option<PathBuf>,
 }
 
 #[derive(Clone)]
@@ -40,12 +86,25 @@ struct AppState {
     secret_names: Vec<String>,
     namespace: String,
     webhook_secrets: Arc<RwLock<HashMap<String, WebhookSecret>>>,
+    api_keys: Arc<RwLock<ApiKeyStore>>,
+    webhook_pubkey: Option<VerifyingKey>,
+    webhook_max_skew_secs: i64,
+    webhook_nonces: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    metrics: Arc<Metrics>,
+    users: Arc<RwLock<UserStore>>,
+    sessions: Arc<RwLock<HashMap<String, Session>>>,
+    secret_cache: Arc<RwLock<cache::SecretCache>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct WebhookSecret {
     name: String,
     fields: HashMap<String, String>,
+    /// Replay-protection fields, required when the webhook is signed.
+    #[serde(default)]
+    nonce: String,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
     #[serde(skip_deserializing)]
     #[serde(default)]
     received_at: String,
@@ -72,51 +131,104 @@ struct SecretQuery {
     field: String,
 }
 
-async fn read_secrets(state: &AppState) -> Result<Vec<SecretData>> {
+/// Classifies a `kube` API error into a metrics outcome label.
+fn fetch_outcome(e: &kube::Error) -> &'static str {
+    match e {
+        kube::Error::Api(resp) if resp.code == StatusCode::NOT_FOUND.as_u16() => "not_found",
+        _ => "error",
+    }
+}
+
+/// Caps the cardinality of the `secret`-labeled metrics by only ever using
+/// the operator's configured `--secrets` names as label values. `name` here
+/// is caller-supplied (the `/secret?name=` query param, or the `name` field
+/// of a webhook body) and unbounded, so anything not in `secret_names` is
+/// folded into a single `"unconfigured"` bucket instead of minting a new
+/// Prometheus time series per request.
+fn metric_secret_label<'a>(state: &AppState, name: &'a str) -> &'a str {
+    if state.secret_names.iter().any(|configured| configured == name) {
+        name
+    } else {
+        "unconfigured"
+    }
+}
+
+/// Live, uncached read of one secret via the Kubernetes API. Used as the
+/// cache-miss fallback (e.g. the watch hasn't synced yet) so the dashboard
+/// never shows nothing just because startup is still in progress.
+async fn fetch_live(state: &AppState, secret_name: &str) -> std::result::Result<Vec<(String, String)>, kube::Error> {
     let secrets_api: Api<Secret> = Api::namespaced(state.client.clone(), &state.namespace);
+
+    let timer = state.metrics.k8s_get_latency.start_timer();
+    let get_result = secrets_api.get(secret_name).await;
+    timer.observe_duration();
+
+    get_result.map(|secret| {
+        let mut data_pairs = Vec::new();
+        if let Some(data) = secret.data {
+            for (key, value) in data {
+                data_pairs.push((key, String::from_utf8_lossy(&value.0).to_string()));
+            }
+        } else if let Some(string_data) = secret.string_data {
+            for (key, value) in string_data {
+                data_pairs.push((key, value));
+            }
+        }
+        data_pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        data_pairs
+    })
+}
+
+async fn read_secrets(state: &AppState) -> Result<Vec<SecretData>> {
     let mut result = Vec::new();
 
     for secret_name in &state.secret_names {
-        match secrets_api.get(secret_name).await {
-            Ok(secret) => {
-                let mut data_pairs = Vec::new();
-                
-                if let Some(data) = secret.data {
-                    for (key, value) in data {
-                        let decoded = String::from_utf8_lossy(&value.0).to_string();
-                        data_pairs.push((key, decoded));
-                    }
-                } else if let Some(string_data) = secret.string_data {
-                    for (key, value) in string_data {
-                        data_pairs.push((key, value));
-                    }
-                }
-                
-                data_pairs.sort_by(|a, b| a.0.cmp(&b.0));
-                
-                result.push(SecretData {
-                    name: secret_name.clone(),
-                    data: data_pairs,
-                    received_at: None,
-                });
-            }
-            Err(e) => {
-                error!("Failed to read secret {}: {}", secret_name, e);
+        let cached = state
+            .secret_cache
+            .read()
+            .ok()
+            .and_then(|cache| cache.get(secret_name).cloned());
+
+        match cached {
+            Some(entry) => {
+                state.metrics.record_fetch(secret_name, "ok");
                 result.push(SecretData {
                     name: secret_name.clone(),
-                    data: vec![("error".to_string(), format!("Failed to read: {}", e))],
-                    received_at: None,
+                    data: entry.data,
+                    received_at: Some(entry.last_synced.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
                 });
             }
+            None => match fetch_live(state, secret_name).await {
+                Ok(data) => {
+                    state.metrics.record_fetch(secret_name, "ok");
+                    result.push(SecretData {
+                        name: secret_name.clone(),
+                        data,
+                        received_at: None,
+                    });
+                }
+                Err(e) => {
+                    error!("Failed to read secret {}: {}", secret_name, e);
+                    state.metrics.record_fetch(secret_name, fetch_outcome(&e));
+                    result.push(SecretData {
+                        name: secret_name.clone(),
+                        data: vec![("error".to_string(), format!("Failed to read: {}", e))],
+                        received_at: None,
+                    });
+                }
+            },
         }
     }
-    
+
     Ok(result)
 }
 
-async fn index_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+async fn index_handler(
+    State(state): State<Arc<AppState>>,
+    key_meta: Option<Extension<KeyMeta>>,
+) -> impl IntoResponse {
     info!("Handling request, fetching secrets: {:?}", state.secret_names);
-    
+
     let mut all_secrets = match read_secrets(&state).await {
         Ok(secrets) => secrets,
         Err(e) => {
@@ -125,14 +237,14 @@ async fn index_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse
                 secrets: vec![],
                 error: Some(format!("Failed to read secrets: {}", e)),
             };
-            
+
             return match template.render() {
                 Ok(html) => Html(html).into_response(),
                 Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read secrets").into_response(),
             };
         }
     };
-    
+
     // Add webhook secrets
     if let Ok(webhook_secrets) = state.webhook_secrets.read() {
         for (_, webhook_secret) in webhook_secrets.iter() {
@@ -140,7 +252,7 @@ async fn index_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse
                 .map(|(k, v)| (k.clone(), v.clone()))
                 .collect();
             data_pairs.sort_by(|a, b| a.0.cmp(&b.0));
-            
+
             all_secrets.push(SecretData {
                 name: webhook_secret.name.clone(),
                 data: data_pairs,
@@ -148,7 +260,14 @@ async fn index_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse
             });
         }
     }
-    
+
+    // A key scoped to `allowed_secrets` must not see any other secret just
+    // because it listed everything for the dashboard view; enforce the same
+    // scoping `secret_handler` applies to individual lookups.
+    if let Some(Extension(key_meta)) = &key_meta {
+        all_secrets.retain(|secret| auth::secret_allowed(key_meta, &secret.name));
+    }
+
     let template = IndexTemplate {
         secrets: all_secrets,
         error: None,
@@ -167,23 +286,91 @@ async fn health_handler() -> impl IntoResponse {
     "OK"
 }
 
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> Response {
+    match state.metrics.render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [("content-type", prometheus::TextEncoder::new().format_type())],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to render metrics: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to render metrics").into_response()
+        }
+    }
+}
+
 async fn webhook_handler(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<WebhookSecret>,
-) -> impl IntoResponse {
-    info!("Received webhook for secret: {}", payload.name);
-    
-    let mut webhook_secret = payload;
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(pubkey) = &state.webhook_pubkey {
+        let signature = match headers.get("x-signature").and_then(|v| v.to_str().ok()) {
+            Some(sig) => sig,
+            None => return (StatusCode::UNAUTHORIZED, "Missing X-Signature header").into_response(),
+        };
+
+        if let Err(e) = webhook::verify_signature(pubkey, &body, signature) {
+            error!("Webhook signature verification failed: {}", e);
+            return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+        }
+    }
+
+    let mut webhook_secret: WebhookSecret = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Failed to parse webhook payload: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid webhook payload").into_response();
+        }
+    };
+
+    info!("Received webhook for secret: {}", webhook_secret.name);
+
+    if state.webhook_pubkey.is_some() {
+        let Some(timestamp) = webhook_secret.timestamp.filter(|_| !webhook_secret.nonce.is_empty()) else {
+            error!("Rejected signed webhook for {} without nonce or timestamp", webhook_secret.name);
+            return (StatusCode::UNAUTHORIZED, "Missing webhook nonce or timestamp").into_response();
+        };
+
+        let mut nonces = match state.webhook_nonces.write() {
+            Ok(nonces) => nonces,
+            Err(e) => {
+                error!("Failed to lock webhook nonce cache: {}", e);
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store webhook").into_response();
+            }
+        };
+
+        if let Err(e) = webhook::check_replay(
+            &mut nonces,
+            &webhook_secret.nonce,
+            timestamp,
+            state.webhook_max_skew_secs,
+        ) {
+            error!("Rejected replayed webhook for {}: {}", webhook_secret.name, e);
+            return (StatusCode::UNAUTHORIZED, "Replayed or expired webhook").into_response();
+        }
+    }
+
     webhook_secret.received_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-    
+
+    let secret_name = webhook_secret.name.clone();
+
     match state.webhook_secrets.write() {
         Ok(mut secrets) => {
-            secrets.insert(webhook_secret.name.clone(), webhook_secret);
-            (StatusCode::OK, "Webhook received")
+            secrets.insert(secret_name.clone(), webhook_secret);
+            state
+                .metrics
+                .webhook_receipts
+                .with_label_values(&[metric_secret_label(&state, &secret_name)])
+                .inc();
+            state.metrics.webhook_secrets_stored.set(secrets.len() as i64);
+            (StatusCode::OK, "Webhook received").into_response()
         }
         Err(e) => {
             error!("Failed to write webhook secret: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store webhook")
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to store webhook").into_response()
         }
     }
 }
@@ -211,50 +398,88 @@ fn generate_totp_code(otpauth_url: &str) -> Option<String> {
 async fn secret_handler(
     Query(params): Query<SecretQuery>,
     State(state): State<Arc<AppState>>,
+    key_meta: Option<Extension<KeyMeta>>,
 ) -> Response {
     info!("Fetching secret: {} field: {}", params.name, params.field);
-    
-    let secrets_api: Api<Secret> = Api::namespaced(state.client.clone(), &state.namespace);
-    
-    match secrets_api.get(&params.name).await {
-        Ok(secret) => {
-            if let Some(data) = secret.data {
-                if let Some(value) = data.get(&params.field) {
-                    let decoded = String::from_utf8_lossy(&value.0).to_string();
-                    
-                    // Check if it's a TOTP URL and generate code
-                    if decoded.starts_with("otpauth://totp/") {
-                        if let Some(code) = generate_totp_code(&decoded) {
-                            return code.into_response();
-                        }
-                    }
-                    
-                    return decoded.into_response();
-                }
-            }
-            
-            if let Some(string_data) = secret.string_data {
-                if let Some(value) = string_data.get(&params.field) {
-                    // Check if it's a TOTP URL and generate code
-                    if value.starts_with("otpauth://totp/") {
-                        if let Some(code) = generate_totp_code(value) {
-                            return code.into_response();
-                        }
-                    }
-                    
-                    return value.clone().into_response();
+
+    if let Some(Extension(key_meta)) = &key_meta {
+        if !auth::secret_allowed(key_meta, &params.name) {
+            return (
+                StatusCode::FORBIDDEN,
+                format!("API key is not scoped for secret '{}'", params.name),
+            )
+                .into_response();
+        }
+    }
+
+    let cached = state
+        .secret_cache
+        .read()
+        .ok()
+        .and_then(|cache| cache.get(&params.name).cloned());
+
+    let data = match cached {
+        Some(entry) => Ok(entry.data),
+        None => fetch_live(&state, &params.name).await,
+    };
+
+    match data {
+        Ok(data) => {
+            let Some((_, value)) = data.iter().find(|(key, _)| key == &params.field) else {
+                state
+                    .metrics
+                    .record_fetch(metric_secret_label(&state, &params.name), "not_found");
+                return (
+                    StatusCode::NOT_FOUND,
+                    format!("Field '{}' not found in secret '{}'", params.field, params.name),
+                )
+                    .into_response();
+            };
+
+            state.metrics.record_fetch(metric_secret_label(&state, &params.name), "ok");
+
+            // Check if it's a TOTP URL and generate code
+            if value.starts_with("otpauth://totp/") {
+                if let Some(code) = generate_totp_code(value) {
+                    return code.into_response();
                 }
             }
-            
-            (StatusCode::NOT_FOUND, format!("Field '{}' not found in secret '{}'", params.field, params.name)).into_response()
+
+            value.clone().into_response()
         }
         Err(e) => {
             error!("Failed to read secret {}: {}", params.name, e);
+            state
+                .metrics
+                .record_fetch(metric_secret_label(&state, &params.name), fetch_outcome(&e));
             (StatusCode::NOT_FOUND, format!("Secret '{}' not found: {}", params.name, e)).into_response()
         }
     }
 }
 
+/// Composes session login and API-key auth as alternatives rather than a
+/// hard AND: a browser with a valid session cookie gets through even with
+/// no key, and an API client with a valid key gets through even with no
+/// cookie. Only used when both `--auth-users-file` and `--api-keys-file`
+/// are configured together.
+async fn require_session_or_api_key(
+    State(state): State<Arc<AppState>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if session::session_valid(&state, request.headers()) {
+        return next.run(request).await;
+    }
+
+    match auth::authenticate(&state, request.headers()) {
+        Ok(meta) => {
+            request.extensions_mut().insert(meta);
+            next.run(request).await
+        }
+        Err(status) => status.into_response(),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
@@ -271,26 +496,123 @@ async fn main() -> Result<()> {
     info!("Namespace: {}", args.namespace);
     
     let client = Client::try_default().await?;
-    
+
+    let api_keys = match &args.api_keys_file {
+        Some(path) => {
+            info!("Loading API keys from {:?}", path);
+            auth::load_api_keys(path)?
+        }
+        None => {
+            info!("No --api-keys-file provided; secret-serving routes are unauthenticated");
+            HashMap::new()
+        }
+    };
+
+    let webhook_pubkey = match &args.webhook_pubkey {
+        Some(path) => {
+            info!("Loading webhook Ed25519 public key from {:?}", path);
+            Some(webhook::load_verifying_key(path)?)
+        }
+        None => {
+            info!("No --webhook-pubkey provided; webhook payloads are not signature-checked");
+            None
+        }
+    };
+
+    let users = match &args.auth_users_file {
+        Some(path) => {
+            info!("Loading web UI users from {:?}", path);
+            session::load_users(path)?
+        }
+        None => {
+            info!("No --auth-users-file provided; the web UI is not login-gated");
+            HashMap::new()
+        }
+    };
+
     let state = Arc::new(AppState {
         client,
         secret_names: args.secrets,
         namespace: args.namespace,
         webhook_secrets: Arc::new(RwLock::new(HashMap::new())),
+        api_keys: Arc::new(RwLock::new(api_keys)),
+        webhook_pubkey,
+        webhook_max_skew_secs: args.webhook_max_skew_secs,
+        webhook_nonces: Arc::new(RwLock::new(HashMap::new())),
+        metrics: Arc::new(Metrics::new()?),
+        users: Arc::new(RwLock::new(users)),
+        sessions: Arc::new(RwLock::new(HashMap::new())),
+        secret_cache: Arc::new(RwLock::new(HashMap::new())),
     });
-    
-    let mut app = Router::new()
+
+    for secret_name in &state.secret_names {
+        cache::spawn_watch(
+            state.client.clone(),
+            state.namespace.clone(),
+            secret_name.clone(),
+            state.secret_cache.clone(),
+        );
+    }
+
+    // `/secret` discloses raw decoded values just like `/`, so the session
+    // gate has to cover both of them, not just the HTML listing page. When
+    // both session login and API keys are configured they're composed as
+    // alternatives (either gets a browser or an API client through), not
+    // stacked as an AND that would lock logged-in browsers out. `/metrics`
+    // leaks configured secret names and read/webhook counts via the `secret`
+    // label, so it sits behind the same gate rather than on the public router.
+    let mut dashboard = Router::new()
         .route("/", get(index_handler))
-        .route("/health", get(health_handler))
-        .route("/secret", get(secret_handler));
-    
+        .route("/secret", get(secret_handler))
+        .route("/metrics", get(metrics_handler));
+
+    dashboard = match (args.auth_users_file.is_some(), args.api_keys_file.is_some()) {
+        (true, true) => {
+            dashboard.layer(middleware::from_fn_with_state(state.clone(), require_session_or_api_key))
+        }
+        (true, false) => dashboard.layer(middleware::from_fn_with_state(state.clone(), session::require_session)),
+        (false, true) => dashboard.layer(middleware::from_fn_with_state(state.clone(), auth::require_api_key)),
+        (false, false) => dashboard,
+    };
+
+    let mut protected = dashboard;
+
     if args.webhook {
         info!("Webhook endpoint enabled at /webhook");
-        app = app.route("/webhook", post(webhook_handler));
+        let mut webhook_router = Router::new().route("/webhook", post(webhook_handler));
+        if args.api_keys_file.is_some() {
+            webhook_router =
+                webhook_router.layer(middleware::from_fn_with_state(state.clone(), auth::require_api_key));
+        }
+        protected = protected.merge(webhook_router);
     }
-    
-    let app = app.with_state(state.clone());
-    
+
+    let app = Router::new()
+        .route("/health", get(health_handler))
+        .route("/login", get(session::login_get).post(session::login_post))
+        .merge(protected)
+        .with_state(state.clone());
+
+    if args.admission_webhook {
+        let admission_cfg = admission::AdmissionConfig {
+            namespace: state.namespace.clone(),
+            service_name: args.admission_service_name,
+            webhook_config_name: args.admission_webhook_config_name,
+            port: args.admission_port,
+            failure_policy: args.admission_failure_policy,
+        };
+        let cert = admission::load_or_generate_serving_cert(state.client.clone(), &admission_cfg).await?;
+
+        info!("Registering ValidatingWebhookConfiguration {}", admission_cfg.webhook_config_name);
+        admission::ensure_registered(state.client.clone(), &admission_cfg, &cert).await?;
+
+        tokio::spawn(async move {
+            if let Err(e) = admission::serve(admission_cfg, cert).await {
+                error!("Admission webhook server exited: {}", e);
+            }
+        });
+    }
+
     let addr = format!("0.0.0.0:{}", args.port);
     info!("Server listening on {}", addr);
     
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+/// Prometheus instrumentation for the service. One instance lives in
+/// `AppState` and is shared by every handler.
+pub struct Metrics {
+    registry: Registry,
+    pub secret_fetches: IntCounterVec,
+    pub webhook_receipts: IntCounterVec,
+    pub webhook_secrets_stored: IntGauge,
+    pub k8s_get_latency: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let secret_fetches = IntCounterVec::new(
+            Opts::new("secret_fetches_total", "Total secret fetches by name and outcome"),
+            &["secret", "outcome"],
+        )
+        .context("failed to create secret_fetches_total counter")?;
+
+        let webhook_receipts = IntCounterVec::new(
+            Opts::new("webhook_receipts_total", "Total webhook payloads received by secret name"),
+            &["secret"],
+        )
+        .context("failed to create webhook_receipts_total counter")?;
+
+        let webhook_secrets_stored = IntGauge::new(
+            "webhook_secrets_stored",
+            "Number of webhook-sourced secrets currently held in memory",
+        )
+        .context("failed to create webhook_secrets_stored gauge")?;
+
+        let k8s_get_latency = Histogram::with_opts(HistogramOpts::new(
+            "k8s_get_latency_seconds",
+            "Latency of Kubernetes API `get` calls for secrets",
+        ))
+        .context("failed to create k8s_get_latency_seconds histogram")?;
+
+        registry.register(Box::new(secret_fetches.clone()))?;
+        registry.register(Box::new(webhook_receipts.clone()))?;
+        registry.register(Box::new(webhook_secrets_stored.clone()))?;
+        registry.register(Box::new(k8s_get_latency.clone()))?;
+
+        Ok(Self {
+            registry,
+            secret_fetches,
+            webhook_receipts,
+            webhook_secrets_stored,
+            k8s_get_latency,
+        })
+    }
+
+    pub fn record_fetch(&self, secret_name: &str, outcome: &str) {
+        self.secret_fetches.with_label_values(&[secret_name, outcome]).inc();
+    }
+
+    /// Renders the registry in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String> {
+        let metric_families = self.registry.gather();
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&metric_families, &mut buffer)
+            .context("failed to encode metrics")?;
+        String::from_utf8(buffer).context("metrics output was not valid utf-8")
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Argon2, Params, Version};
+use askama::Template;
+use axum::{
+    extract::{Form, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use serde::Deserialize;
+
+use crate::AppState;
+
+pub const SESSION_COOKIE: &str = "session";
+pub const SESSION_TTL_SECS: i64 = 8 * 3600;
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub username: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub type SessionStore = HashMap<String, Session>;
+/// username -> Argon2id password hash, PHC string format.
+pub type UserStore = HashMap<String, String>;
+
+pub fn load_users(path: &Path) -> Result<UserStore> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read auth users file {:?}", path))?;
+    serde_json::from_str(&raw).with_context(|| format!("failed to parse auth users file {:?}", path))
+}
+
+/// OWASP's current baseline Argon2id recommendation (19 MiB, 2 iterations,
+/// 1 lane). Bumping this is all that's needed to roll out stronger
+/// parameters; `verify_and_maybe_rehash` upgrades existing hashes in place.
+fn current_params() -> Params {
+    Params::new(19 * 1024, 2, 1, None).expect("static Argon2 params are valid")
+}
+
+fn hasher() -> Argon2<'static> {
+    Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, current_params())
+}
+
+/// A PHC hash of a fixed dummy password, computed once at `current_params`
+/// cost. `verify_and_maybe_rehash` verifies against this when `username`
+/// doesn't exist, so an unknown-username login takes about as long as a
+/// wrong-password one instead of returning instantly and leaking which
+/// usernames are registered.
+fn dummy_hash() -> &'static str {
+    static DUMMY: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    DUMMY.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        hasher()
+            .hash_password(b"dummy-password-for-constant-time-check", &salt)
+            .expect("hashing a static dummy password cannot fail")
+            .to_string()
+    })
+}
+
+/// Verifies `password` against the stored hash for `username`, never
+/// comparing plaintext directly. If the password is correct but was hashed
+/// with weaker parameters than `current_params`, rehashes it and updates
+/// `users` in place so the next deploy's parameter bump is picked up
+/// without forcing a password reset.
+pub fn verify_and_maybe_rehash(users: &mut UserStore, username: &str, password: &str) -> bool {
+    let Some(stored) = users.get(username) else {
+        let dummy = PasswordHash::new(dummy_hash()).expect("dummy hash is a valid PHC string");
+        let _ = hasher().verify_password(password.as_bytes(), &dummy);
+        return false;
+    };
+
+    let parsed = match PasswordHash::new(stored) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+
+    if hasher().verify_password(password.as_bytes(), &parsed).is_err() {
+        return false;
+    }
+
+    let target = current_params();
+    let needs_rehash = match Params::try_from(&parsed) {
+        Ok(params) => params.m_cost() < target.m_cost() || params.t_cost() < target.t_cost(),
+        Err(_) => true,
+    };
+
+    if needs_rehash {
+        let salt = SaltString::generate(&mut OsRng);
+        if let Ok(rehashed) = hasher().hash_password(password.as_bytes(), &salt) {
+            users.insert(username.to_string(), rehashed.to_string());
+        }
+    }
+
+    true
+}
+
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+pub fn create_session(sessions: &mut SessionStore, username: &str) -> String {
+    let id = new_session_id();
+    sessions.insert(
+        id.clone(),
+        Session {
+            username: username.to_string(),
+            expires_at: Utc::now() + Duration::seconds(SESSION_TTL_SECS),
+        },
+    );
+    id
+}
+
+fn build_session_cookie(session_id: &str) -> String {
+    format!(
+        "{}={}; HttpOnly; Secure; SameSite=Strict; Path=/; Max-Age={}",
+        SESSION_COOKIE, session_id, SESSION_TTL_SECS
+    )
+}
+
+fn extract_session_id(headers: &axum::http::HeaderMap) -> Option<String> {
+    let cookie_header = headers.get(header::COOKIE)?.to_str().ok()?;
+    cookie_header.split(';').find_map(|part| {
+        part.trim()
+            .strip_prefix(&format!("{}=", SESSION_COOKIE))
+            .map(str::to_string)
+    })
+}
+
+/// Returns whether `headers` carries a cookie for a session that still
+/// exists and hasn't expired. Shared by the `require_session` middleware and
+/// by callers composing session login with another gate (e.g. API keys).
+pub fn session_valid(state: &AppState, headers: &axum::http::HeaderMap) -> bool {
+    let Some(session_id) = extract_session_id(headers) else {
+        return false;
+    };
+
+    match state.sessions.read() {
+        Ok(sessions) => sessions
+            .get(&session_id)
+            .map(|s| s.expires_at > Utc::now())
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Redirects unauthenticated browser requests to `/login`. Only wired up
+/// when `--auth-users-file` is set; otherwise the dashboard stays open.
+pub async fn require_session(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    if session_valid(&state, request.headers()) {
+        next.run(request).await
+    } else {
+        Redirect::to("/login").into_response()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    error: Option<String>,
+}
+
+fn render_login(error: Option<String>) -> Response {
+    match (LoginTemplate { error }).render() {
+        Ok(html) => Html(html).into_response(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to render login form").into_response(),
+    }
+}
+
+pub async fn login_get() -> Response {
+    render_login(None)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+}
+
+pub async fn login_post(State(state): State<Arc<AppState>>, Form(form): Form<LoginForm>) -> Response {
+    let ok = match state.users.write() {
+        Ok(mut users) => verify_and_maybe_rehash(&mut users, &form.username, &form.password),
+        Err(_) => false,
+    };
+
+    if !ok {
+        return render_login(Some("Invalid username or password".to_string()));
+    }
+
+    let session_id = match state.sessions.write() {
+        Ok(mut sessions) => create_session(&mut sessions, &form.username),
+        Err(_) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create session").into_response();
+        }
+    };
+
+    let mut response = Redirect::to("/").into_response();
+    response
+        .headers_mut()
+        .insert(header::SET_COOKIE, build_session_cookie(&session_id).parse().unwrap());
+    response
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// Default replay window: a signed webhook older than this is rejected.
+pub const DEFAULT_MAX_SKEW_SECS: i64 = 300;
+
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read webhook pubkey file {:?}", path))?;
+    let bytes = hex::decode(raw.trim())
+        .with_context(|| format!("webhook pubkey file {:?} is not valid hex", path))?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("webhook pubkey must be 32 bytes"))?;
+    VerifyingKey::from_bytes(&bytes).context("invalid ed25519 public key")
+}
+
+/// Verifies a hex-encoded detached signature over the raw request body.
+pub fn verify_signature(key: &VerifyingKey, body: &[u8], signature_hex: &str) -> Result<()> {
+    let sig_bytes = hex::decode(signature_hex.trim()).context("signature header is not valid hex")?;
+    let sig_bytes: [u8; 64] = sig_bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("signature must be 64 bytes"))?;
+    let signature = Signature::from_bytes(&sig_bytes);
+    key.verify(body, &signature).context("signature verification failed")
+}
+
+/// Rejects a payload timestamped outside the replay window, and prunes
+/// `seen_nonces` down to the same window so it doesn't grow without bound.
+pub fn check_replay(
+    seen_nonces: &mut HashMap<String, DateTime<Utc>>,
+    nonce: &str,
+    timestamp: DateTime<Utc>,
+    max_skew_secs: i64,
+) -> Result<()> {
+    let window = Duration::seconds(max_skew_secs);
+    let now = Utc::now();
+
+    if now.signed_duration_since(timestamp) > window {
+        bail!("webhook timestamp is older than the {}s replay window", max_skew_secs);
+    }
+
+    seen_nonces.retain(|_, seen_at| now.signed_duration_since(*seen_at) <= window);
+
+    if seen_nonces.contains_key(nonce) {
+        bail!("nonce already seen within the replay window");
+    }
+
+    seen_nonces.insert(nonce.to_string(), now);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn keypair() -> (SigningKey, VerifyingKey) {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let verifying_key = signing_key.verifying_key();
+        (signing_key, verifying_key)
+    }
+
+    #[test]
+    fn verify_signature_accepts_a_correctly_signed_body() {
+        let (signing_key, verifying_key) = keypair();
+        let body = b"hello world";
+        let signature_hex = hex::encode(signing_key.sign(body).to_bytes());
+
+        assert!(verify_signature(&verifying_key, body, &signature_hex).is_ok());
+    }
+
+    #[test]
+    fn verify_signature_rejects_a_tampered_body() {
+        let (signing_key, verifying_key) = keypair();
+        let signature_hex = hex::encode(signing_key.sign(b"hello world").to_bytes());
+
+        assert!(verify_signature(&verifying_key, b"hello worlD", &signature_hex).is_err());
+    }
+
+    #[test]
+    fn check_replay_rejects_a_nonce_reused_within_the_window() {
+        let mut seen_nonces = HashMap::new();
+        let now = Utc::now();
+
+        assert!(check_replay(&mut seen_nonces, "nonce-a", now, DEFAULT_MAX_SKEW_SECS).is_ok());
+        assert!(check_replay(&mut seen_nonces, "nonce-a", now, DEFAULT_MAX_SKEW_SECS).is_err());
+    }
+
+    #[test]
+    fn check_replay_allows_a_nonce_reused_once_it_has_aged_out_of_the_window() {
+        let mut seen_nonces = HashMap::new();
+        let stale = Utc::now() - Duration::seconds(DEFAULT_MAX_SKEW_SECS + 10);
+        seen_nonces.insert("nonce-b".to_string(), stale);
+
+        assert!(check_replay(&mut seen_nonces, "nonce-b", Utc::now(), DEFAULT_MAX_SKEW_SECS).is_ok());
+    }
+
+    #[test]
+    fn check_replay_accepts_a_timestamp_just_inside_the_skew_boundary() {
+        let mut seen_nonces = HashMap::new();
+        let timestamp = Utc::now() - Duration::seconds(DEFAULT_MAX_SKEW_SECS - 1);
+
+        assert!(check_replay(&mut seen_nonces, "nonce-c", timestamp, DEFAULT_MAX_SKEW_SECS).is_ok());
+    }
+
+    #[test]
+    fn check_replay_rejects_a_timestamp_just_past_the_skew_boundary() {
+        let mut seen_nonces = HashMap::new();
+        let timestamp = Utc::now() - Duration::seconds(DEFAULT_MAX_SKEW_SECS + 1);
+
+        assert!(check_replay(&mut seen_nonces, "nonce-d", timestamp, DEFAULT_MAX_SKEW_SECS).is_err());
+    }
+}